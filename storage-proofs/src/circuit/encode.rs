@@ -0,0 +1,127 @@
+//! In-circuit companion to `crypto::sloth`'s `encode`/`decode`.
+//!
+//! The out-of-circuit functions remain the witness generators; these
+//! gadgets let a circuit (e.g. the DrgPoRep replication circuit, see
+//! `circuit::drg`) enforce that a replica leaf was produced by actually
+//! encoding the corresponding data leaf under the derived key, rather than
+//! merely asserting merkle membership of both.
+
+use bellman::{ConstraintSystem, SynthesisError};
+use pairing::Engine;
+use sapling_crypto::circuit::num::AllocatedNum;
+
+/// Enforces `ciphertext = plaintext + key` and returns the allocated
+/// `ciphertext`.
+pub fn encode_circuit<E, CS>(
+    mut cs: CS,
+    key: &AllocatedNum<E>,
+    plaintext: &AllocatedNum<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let ciphertext = AllocatedNum::alloc(cs.namespace(|| "ciphertext"), || {
+        let mut value = plaintext.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        value.add_assign(&key.get_value().ok_or(SynthesisError::AssignmentMissing)?);
+        Ok(value)
+    })?;
+
+    // plaintext + key = ciphertext
+    cs.enforce(
+        || "encode constraint",
+        |lc| lc + plaintext.get_variable() + key.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + ciphertext.get_variable(),
+    );
+
+    Ok(ciphertext)
+}
+
+/// Enforces `plaintext = ciphertext - key` and returns the allocated
+/// `plaintext`.
+pub fn decode_circuit<E, CS>(
+    mut cs: CS,
+    key: &AllocatedNum<E>,
+    ciphertext: &AllocatedNum<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: Engine,
+    CS: ConstraintSystem<E>,
+{
+    let plaintext = AllocatedNum::alloc(cs.namespace(|| "plaintext"), || {
+        let mut value = ciphertext.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        value.sub_assign(&key.get_value().ok_or(SynthesisError::AssignmentMissing)?);
+        Ok(value)
+    })?;
+
+    // plaintext + key = ciphertext
+    cs.enforce(
+        || "decode constraint",
+        |lc| lc + plaintext.get_variable() + key.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + ciphertext.get_variable(),
+    );
+
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::PrimeField;
+    use pairing::bls12_381::{Bls12, Fr, FrRepr};
+    use proptest::{prop_compose, proptest, proptest_helper};
+
+    use crate::circuit::test::*;
+    use crate::crypto::sloth;
+
+    // the modulus from `bls12_381::Fr`
+    const MODULUS: [u64; 4] = [
+        0xffffffff00000001,
+        0x53bda402fffe5bfe,
+        0x3339d80809a1d805,
+        0x73eda753299d7d48,
+    ];
+
+    prop_compose! {
+        fn arb_fr()(a in 0..MODULUS[0], b in 0..MODULUS[1], c in 0..MODULUS[2], d in 0..MODULUS[3]) -> Fr {
+            Fr::from_repr(FrRepr([a, b, c, d])).unwrap()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn encode_circuit_matches_witness(key in arb_fr(), plaintext in arb_fr()) {
+            let mut cs = TestConstraintSystem::<Bls12>::new();
+
+            let key_num =
+                AllocatedNum::alloc(cs.namespace(|| "key"), || Ok(key)).unwrap();
+            let plaintext_num =
+                AllocatedNum::alloc(cs.namespace(|| "plaintext"), || Ok(plaintext)).unwrap();
+
+            let ciphertext_num =
+                encode_circuit(cs.namespace(|| "encode"), &key_num, &plaintext_num).unwrap();
+
+            assert!(cs.is_satisfied());
+
+            // `sloth::encode` is defined against `paired::bls12_381::Bls12`,
+            // a separate crate from the `pairing` types the circuit gadget
+            // above uses, so bridge `key`/`plaintext` across before calling
+            // it and bridge the result back for comparison.
+            let expected_paired = sloth::encode::<paired::bls12_381::Bls12>(
+                &sloth::fr_to_paired(key),
+                &sloth::fr_to_paired(plaintext),
+            );
+            let expected = sloth::fr_from_paired(expected_paired);
+            assert_eq!(ciphertext_num.get_value().unwrap(), expected);
+
+            let decoded_num =
+                decode_circuit(cs.namespace(|| "decode"), &key_num, &ciphertext_num).unwrap();
+
+            assert!(cs.is_satisfied());
+            assert_eq!(decoded_num.get_value().unwrap(), plaintext);
+        }
+    }
+}
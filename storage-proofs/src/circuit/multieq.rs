@@ -0,0 +1,149 @@
+//! Coalesces many small equality assertions into a handful of full-width
+//! `enforce` calls.
+//!
+//! This mirrors the `MultiEq` wrapper sapling-crypto's blake2s circuit
+//! introduced: rather than emitting one `enforce` per equality check,
+//! `MultiEq` bit-packs the left- and right-hand sides of several checks of
+//! known bit-width into a single accumulator (scaling each by `2^bits_used`
+//! so they land in disjoint bit ranges), and only emits a real constraint
+//! once the next check would overflow the field's capacity.
+//!
+//! This only pays off for checks substantially narrower than the field's
+//! capacity (e.g. packed path bits): a check on a full field element (such
+//! as a Merkle root) already exceeds half the capacity, so no two of them
+//! ever share an accumulator and batching them costs exactly as much as
+//! enforcing each one directly. `PoRCCircuit` (see `circuit::porc`) enforces
+//! its root equalities directly for this reason.
+
+use bellman::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+use pairing::{Engine, Field, PrimeField};
+
+pub struct MultiEq<E: Engine, CS: ConstraintSystem<E>> {
+    cs: CS,
+    ops: usize,
+    bits_used: usize,
+    lhs: LinearCombination<E>,
+    rhs: LinearCombination<E>,
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> MultiEq<E, CS> {
+    pub fn new(cs: CS) -> Self {
+        MultiEq {
+            cs,
+            ops: 0,
+            bits_used: 0,
+            lhs: LinearCombination::zero(),
+            rhs: LinearCombination::zero(),
+        }
+    }
+
+    fn flush_accumulator(&mut self) {
+        if self.bits_used == 0 {
+            return;
+        }
+
+        let ops = self.ops;
+        let lhs = std::mem::replace(&mut self.lhs, LinearCombination::zero());
+        let rhs = std::mem::replace(&mut self.rhs, LinearCombination::zero());
+
+        self.cs.enforce(
+            || format!("multieq {}", ops),
+            |_| lhs,
+            |lc| lc + CS::one(),
+            |_| rhs,
+        );
+
+        self.bits_used = 0;
+        self.ops += 1;
+    }
+
+    /// Flushes any pending equality checks into a real constraint. Must be
+    /// called before relying on an accumulated check having actually been
+    /// enforced against the underlying constraint system (e.g. before
+    /// `inputize`-ing a value whose correctness the accumulated checks
+    /// establish).
+    pub fn flush(&mut self) {
+        self.flush_accumulator();
+    }
+
+    /// Enforces `a == b`, given that both are known to fit in `num_bits`
+    /// bits. The check is coalesced with any pending ones on this
+    /// accumulator, flushing first if the combined width would overflow
+    /// the field's capacity.
+    pub fn enforce_equal(
+        &mut self,
+        num_bits: usize,
+        a: &LinearCombination<E>,
+        b: &LinearCombination<E>,
+    ) {
+        let capacity = E::Fr::CAPACITY as usize;
+
+        if self.bits_used + num_bits > capacity {
+            self.flush_accumulator();
+        }
+
+        let coeff = E::Fr::from_str("2").unwrap().pow(&[self.bits_used as u64]);
+        self.lhs = std::mem::replace(&mut self.lhs, LinearCombination::zero()) + (coeff, a);
+        self.rhs = std::mem::replace(&mut self.rhs, LinearCombination::zero()) + (coeff, b);
+        self.bits_used += num_bits;
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> Drop for MultiEq<E, CS> {
+    fn drop(&mut self) {
+        self.flush_accumulator();
+    }
+}
+
+impl<E: Engine, CS: ConstraintSystem<E>> ConstraintSystem<E> for MultiEq<E, CS> {
+    type Root = Self;
+
+    fn one() -> Variable {
+        CS::one()
+    }
+
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc(annotation, f)
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<E::Fr, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.cs.alloc_input(annotation, f)
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+        LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+    {
+        self.cs.enforce(annotation, a, b, c)
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.cs.get_root().push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self) {
+        self.cs.get_root().pop_namespace()
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
@@ -0,0 +1,222 @@
+//! An in-circuit Poseidon hash.
+//!
+//! Poseidon is used as a drop-in replacement for Pedersen hashing in the
+//! `PoRCCircuit` merkle ascent (see `circuit::porc`): it costs a handful of
+//! multiplication constraints per round instead of hundreds of constraints
+//! per Pedersen personalization, which matters a great deal once trees use
+//! wide arities.
+//!
+//! The construction is the usual Poseidon sponge: `R_f` full rounds (the
+//! x^5 S-box applied to every element of the state) split evenly around
+//! `R_p` partial rounds (the S-box applied only to the first element),
+//! with per-round constants added before the S-box and a fixed MDS matrix
+//! mixing the state after it. The state's first element doubles as the
+//! capacity; the remaining `width - 1` elements are the rate, i.e. the
+//! hash's preimage.
+
+use bellman::{ConstraintSystem, SynthesisError};
+use pairing::{Field, PrimeField};
+use sapling_crypto::circuit::num::AllocatedNum;
+use sapling_crypto::jubjub::JubjubEngine;
+
+/// Round constants and MDS matrix for a Poseidon instance operating on a
+/// state of `width` field elements (`width = preimage arity + 1`, the `+ 1`
+/// being the capacity element).
+pub struct PoseidonParams<E: JubjubEngine> {
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    round_constants: Vec<E::Fr>,
+    mds: Vec<Vec<E::Fr>>,
+}
+
+impl<E: JubjubEngine> PoseidonParams<E> {
+    /// Builds a Poseidon parameter set for the given state width.
+    ///
+    /// Round constants and the MDS matrix are derived deterministically
+    /// rather than loaded from a table, which keeps this gadget
+    /// self-contained. A production deployment should instead instantiate
+    /// `PoseidonParams` from constants produced by the standard Poseidon
+    /// Grain LFSR generator so the instance has been vetted against known
+    /// attacks.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let total_rounds = full_rounds + partial_rounds;
+
+        let mut round_constants = Vec::with_capacity(total_rounds * width);
+        let mut acc = E::Fr::from_str("5").expect("5 is a valid field element");
+        for _ in 0..(total_rounds * width) {
+            acc.square();
+            acc.add_assign(&E::Fr::one());
+            round_constants.push(acc);
+        }
+
+        // A Cauchy-style MDS matrix: `mds[i][j] = 1 / (x_i + y_j)` with
+        // `x_i = i + 1` and `y_j = width + j + 1`, so every denominator is
+        // non-zero and every row/column distinct.
+        let mut mds = Vec::with_capacity(width);
+        for i in 0..width {
+            let mut row = Vec::with_capacity(width);
+            for j in 0..width {
+                let xi = E::Fr::from_str(&(i + 1).to_string()).unwrap();
+                let mut denom = E::Fr::from_str(&(width + j + 1).to_string()).unwrap();
+                denom.add_assign(&xi);
+                row.push(denom.inverse().expect("non-zero by construction"));
+            }
+            mds.push(row);
+        }
+
+        PoseidonParams {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+}
+
+/// Absorbs `preimage` (of length `params.width - 1`) and squeezes a single
+/// field element.
+pub fn poseidon_hash<E, CS>(
+    mut cs: CS,
+    preimage: &[AllocatedNum<E>],
+    params: &PoseidonParams<E>,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: JubjubEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(
+        preimage.len() + 1,
+        params.width,
+        "preimage must exactly fill the sponge's rate"
+    );
+
+    // State = [capacity, preimage...].
+    let mut state: Vec<AllocatedNum<E>> = Vec::with_capacity(params.width);
+    let capacity = AllocatedNum::alloc(cs.namespace(|| "capacity element"), || {
+        Ok(E::Fr::zero())
+    })?;
+    cs.enforce(
+        || "capacity element is zero",
+        |lc| lc + capacity.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc,
+    );
+    state.push(capacity);
+    state.extend(preimage.iter().cloned());
+
+    let half_full = params.full_rounds / 2;
+    let total_rounds = params.full_rounds + params.partial_rounds;
+
+    for round in 0..total_rounds {
+        let mut cs = cs.namespace(|| format!("round {}", round));
+        let is_full_round = round < half_full || round >= half_full + params.partial_rounds;
+
+        // Add the round constants.
+        for (i, elem) in state.iter_mut().enumerate() {
+            let constant = params.round_constants[round * params.width + i];
+            *elem = add_constant(cs.namespace(|| format!("add constant {}", i)), elem, constant)?;
+        }
+
+        // x^5 S-box: every element in a full round, only the capacity
+        // element in a partial round.
+        if is_full_round {
+            for (i, elem) in state.iter_mut().enumerate() {
+                *elem = pow5(cs.namespace(|| format!("sbox {}", i)), elem)?;
+            }
+        } else {
+            state[0] = pow5(cs.namespace(|| "sbox 0"), &state[0])?;
+        }
+
+        // Mix the state through the fixed MDS matrix.
+        let mut mixed = Vec::with_capacity(params.width);
+        for row in 0..params.width {
+            mixed.push(mds_dot(
+                cs.namespace(|| format!("mds row {}", row)),
+                &state,
+                &params.mds[row],
+            )?);
+        }
+        state = mixed;
+    }
+
+    Ok(state[0].clone())
+}
+
+/// Returns `elem + constant`, where `constant` is a public field element
+/// (not a witnessed variable).
+fn add_constant<E, CS>(
+    mut cs: CS,
+    elem: &AllocatedNum<E>,
+    constant: E::Fr,
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: JubjubEngine,
+    CS: ConstraintSystem<E>,
+{
+    let value = elem.get_value().map(|mut v| {
+        v.add_assign(&constant);
+        v
+    });
+    let out = AllocatedNum::alloc(cs.namespace(|| "add constant"), || {
+        value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+
+    cs.enforce(
+        || "add constant constraint",
+        |lc| lc + elem.get_variable() + (constant, CS::one()),
+        |lc| lc + CS::one(),
+        |lc| lc + out.get_variable(),
+    );
+
+    Ok(out)
+}
+
+/// Returns `x^5`, using three multiplication constraints.
+fn pow5<E, CS>(mut cs: CS, x: &AllocatedNum<E>) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: JubjubEngine,
+    CS: ConstraintSystem<E>,
+{
+    let x2 = x.mul(cs.namespace(|| "x^2"), x)?;
+    let x4 = x2.mul(cs.namespace(|| "x^4"), &x2)?;
+    x4.mul(cs.namespace(|| "x^5"), x)
+}
+
+/// Computes `sum_j state[j] * row[j]` in a single constraint, since `row` is
+/// a vector of public constants rather than witnessed variables.
+fn mds_dot<E, CS>(
+    mut cs: CS,
+    state: &[AllocatedNum<E>],
+    row: &[E::Fr],
+) -> Result<AllocatedNum<E>, SynthesisError>
+where
+    E: JubjubEngine,
+    CS: ConstraintSystem<E>,
+{
+    let value = state.iter().zip(row.iter()).try_fold(E::Fr::zero(), |mut acc, (s, c)| {
+        let mut term = s.get_value()?;
+        term.mul_assign(c);
+        acc.add_assign(&term);
+        Some(acc)
+    });
+
+    let out = AllocatedNum::alloc(cs.namespace(|| "mds output"), || {
+        value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+
+    cs.enforce(
+        || "mds row constraint",
+        |lc| {
+            state
+                .iter()
+                .zip(row.iter())
+                .fold(lc, |lc, (s, c)| lc + (*c, s.get_variable()))
+        },
+        |lc| lc + CS::one(),
+        |lc| lc + out.get_variable(),
+    );
+
+    Ok(out)
+}
@@ -20,6 +20,25 @@ pub fn decode<E: Engine>(key: &E::Fr, ciphertext: &E::Fr) -> E::Fr {
     plaintext
 }
 
+/// Bridges a `pairing`-typed field element (the type the in-circuit
+/// gadgets in `circuit::encode`/`circuit::drg` are built against) to the
+/// `paired`-typed one `encode`/`decode` above are defined against, and
+/// back. Both crates describe the identical BLS12-381 scalar field with
+/// the same little-endian `[u64; 4]` representation, so the conversion is
+/// just a repr round-trip; it exists purely so tests outside this module
+/// can exercise the real vanilla functions against circuit-gadget values.
+#[cfg(test)]
+pub(crate) fn fr_to_paired(fr: pairing::bls12_381::Fr) -> paired::bls12_381::Fr {
+    use pairing::PrimeField as _;
+    paired::bls12_381::Fr::from_repr(paired::bls12_381::FrRepr(fr.into_repr().0)).unwrap()
+}
+
+#[cfg(test)]
+pub(crate) fn fr_from_paired(fr: paired::bls12_381::Fr) -> pairing::bls12_381::Fr {
+    use ff::PrimeField as _;
+    pairing::bls12_381::Fr::from_repr(pairing::bls12_381::FrRepr(fr.into_repr().0)).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;